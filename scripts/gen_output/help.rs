@@ -5,14 +5,19 @@ edition = "2021"
 
 [dependencies]
 clap = { version = "4", features = ["derive"] }
+flate2 = "1"
 indexmap = "2"
 pathdiff = "0.2"
 regex = "1"
+tar = "0.4"
 ---
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use indexmap::IndexMap;
 use regex::Regex;
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::iter::once;
@@ -20,6 +25,7 @@ use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::str;
 use std::sync::LazyLock;
+use std::thread;
 use std::{fmt, process};
 
 const SECTION_START: &str = "<!-- CLI_REFERENCE START -->";
@@ -67,10 +73,43 @@ struct Args {
     #[arg(long)]
     root_summary: bool,
 
+    /// Render `Options:`/`Arguments:` as markdown tables instead of a raw `--help` block.
+    #[arg(long)]
+    structured: bool,
+
+    /// Compare the generated CLI surface against a previous output directory and
+    /// write a `CHANGELOG.md` of what changed.
+    ///
+    /// The baseline must have been generated in the default (raw ```bash block) mode; a
+    /// `--structured` baseline has no fenced `--help` block to re-parse, so every command
+    /// would be reported as added.
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Pack the generated output tree into a gzip-compressed tarball at this path.
+    #[arg(long)]
+    archive: Option<PathBuf>,
+
+    /// Remove the generated files after archiving (only meaningful with `--archive`).
+    #[arg(long)]
+    archive_clean: bool,
+
     /// Print verbose output
     #[arg(short, long)]
     verbose: bool,
 
+    /// Emit shell completion scripts for each root command into the output directory.
+    #[arg(long, value_enum)]
+    completions: Option<Shell>,
+
+    /// Collect per-command `--help` failures into a summary instead of aborting the run.
+    #[arg(long)]
+    continue_on_error: bool,
+
+    /// Number of concurrent `--help` invocations (defaults to the available parallelism).
+    #[arg(long)]
+    jobs: Option<usize>,
+
     /// Commands to generate markdown for.
     #[arg(required = true, num_args = 1..)]
     commands: Vec<PathBuf>,
@@ -96,44 +135,68 @@ fn main() -> io::Result<()> {
     let out_dir = args.out_dir;
     fs::create_dir_all(&out_dir)?;
 
-    let mut todo_iter: Vec<Cmd> = args
-        .commands
-        .iter()
-        .rev() // reverse to keep the order (pop)
-        .map(Cmd::new)
-        .collect();
-    let mut output = IndexMap::new(); // keep the order in which entries are added
+    let jobs = args
+        .jobs
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .max(1);
 
-    // Iterate over all commands and their subcommands.
-    while let Some(cmd) = todo_iter.pop() {
-        let (new_subcmds, stdout) = get_entry(&cmd)?;
-        if args.verbose && !new_subcmds.is_empty() {
-            println!(
-                "Found subcommands for \"{}\": {:?}",
-                cmd.command_name(),
-                new_subcmds
-            );
-        }
-        // Add new subcommands to todo_iter (so that they are processed in the correct order).
-        for subcmd in new_subcmds.into_iter().rev() {
-            let new_subcmds: Vec<_> = cmd
-                .subcommands
-                .iter()
-                .cloned()
-                .chain(once(subcmd))
-                .collect();
+    let roots: Vec<Cmd> = args.commands.iter().map(Cmd::new).collect();
 
-            todo_iter.push(Cmd {
-                cmd: cmd.cmd,
-                subcommands: new_subcmds,
-            });
+    // Discover the command tree level by level, fanning out each level's `--help`
+    // invocations across a bounded worker pool. Results are keyed by command so the
+    // final ordering can be reconstructed deterministically regardless of completion order.
+    let mut subcommands: HashMap<Cmd, Vec<String>> = HashMap::new();
+    let mut stdouts: HashMap<Cmd, String> = HashMap::new();
+    let mut failures: Vec<(Cmd, String)> = Vec::new();
+
+    let mut frontier = roots.clone();
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        for (cmd, result) in run_help(&frontier, jobs) {
+            match result {
+                Ok((new_subcmds, stdout)) => {
+                    if args.verbose && !new_subcmds.is_empty() {
+                        println!(
+                            "Found subcommands for \"{}\": {:?}",
+                            cmd.command_name(),
+                            new_subcmds
+                        );
+                    }
+                    for subcmd in &new_subcmds {
+                        next.push(Cmd {
+                            cmd: cmd.cmd,
+                            subcommands: cmd
+                                .subcommands
+                                .iter()
+                                .cloned()
+                                .chain(once(subcmd.clone()))
+                                .collect(),
+                        });
+                    }
+                    subcommands.insert(cmd.clone(), new_subcmds);
+                    stdouts.insert(cmd, stdout);
+                }
+                Err(e) if args.continue_on_error => {
+                    eprintln!("Skipping \"{cmd}\": {e}");
+                    failures.push((cmd, e.to_string()));
+                }
+                Err(e) => return Err(e),
+            }
         }
-        output.insert(cmd, stdout);
+        frontier = next;
+    }
+
+    // Reconstruct the deterministic depth-first pre-order used for SUMMARY.md.
+    let mut output = IndexMap::new(); // keep the order in which entries are added
+    for root in &roots {
+        insert_ordered(root, &subcommands, &mut stdouts, &mut output);
     }
 
     // Generate markdown files.
+    let generated: HashSet<String> = output.keys().map(|cmd| cmd.to_string()).collect();
     for (cmd, stdout) in &output {
-        cmd_markdown(&out_dir, cmd, stdout)?;
+        cmd_markdown(&out_dir, cmd, stdout, args.structured, &generated)?;
     }
 
     // Generate SUMMARY.md.
@@ -171,11 +234,117 @@ fn main() -> io::Result<()> {
         update_root_summary(path, &root_summary)?;
     }
 
+    // Diff against a baseline output directory and emit a changelog.
+    if let Some(baseline_dir) = &args.baseline {
+        let current = build_models(&output);
+        let baseline = load_baseline_models(baseline_dir)?;
+        let changelog = diff_models(&baseline, &current);
+        let path = out_dir.join("CHANGELOG.md");
+        if args.verbose {
+            println!("Writing CHANGELOG.md to \"{}\"", path.to_string_lossy());
+        }
+        write_file(&path, &changelog)?;
+    }
+
+    // Generate shell completion scripts next to the markdown.
+    if let Some(shell) = args.completions {
+        if args.verbose {
+            println!("Writing {:?} completions to \"{}\"", shell, out_dir.to_string_lossy());
+        }
+        generate_completions(&out_dir, shell, &output)?;
+    }
+
+    // Pack the generated tree into a gzip tarball.
+    if let Some(archive_path) = &args.archive {
+        if args.verbose {
+            println!("Writing archive to \"{}\"", archive_path.to_string_lossy());
+        }
+        create_archive(&out_dir, archive_path, &output, args.archive_clean)?;
+    }
+
+    // Report any commands skipped under --continue-on-error and fail the run.
+    if !failures.is_empty() {
+        eprintln!("\n{} command(s) failed and were skipped:", failures.len());
+        for (cmd, err) in &failures {
+            eprintln!("  - {cmd}: {err}");
+        }
+        process::exit(1);
+    }
+
     Ok(())
 }
 
+/// The outcome of a single `--help` invocation: the discovered subcommands and raw stdout.
+type HelpResult = io::Result<(Vec<String>, String)>;
+
+/// A command paired with the outcome of its `--help` invocation.
+type HelpOutcome<'a> = (Cmd<'a>, HelpResult);
+
+/// Runs `--help` for every command in the frontier, fanning out across a bounded pool.
+///
+/// Results are returned in the same order as `frontier`, so callers can rely on a stable
+/// association between a command and its outcome regardless of which worker finished first.
+fn run_help<'a>(frontier: &[Cmd<'a>], jobs: usize) -> Vec<HelpOutcome<'a>> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    let cursor = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<HelpResult>>> =
+        frontier.iter().map(|_| Mutex::new(None)).collect();
+    let workers = jobs.min(frontier.len()).max(1);
+
+    thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let i = cursor.fetch_add(1, Ordering::Relaxed);
+                match frontier.get(i) {
+                    Some(cmd) => *results[i].lock().unwrap() = Some(get_entry(cmd)),
+                    None => break,
+                }
+            });
+        }
+    });
+
+    frontier
+        .iter()
+        .cloned()
+        .zip(results)
+        .map(|(cmd, slot)| (cmd, slot.into_inner().unwrap().expect("worker filled slot")))
+        .collect()
+}
+
+/// Inserts a command and its subcommands into `output` in depth-first pre-order.
+///
+/// Missing entries (e.g. commands skipped after a failure) prune their subtree, keeping the
+/// ordering deterministic and independent of discovery completion order.
+fn insert_ordered<'a>(
+    cmd: &Cmd<'a>,
+    subcommands: &HashMap<Cmd<'a>, Vec<String>>,
+    stdouts: &mut HashMap<Cmd<'a>, String>,
+    output: &mut IndexMap<Cmd<'a>, String>,
+) {
+    let Some(stdout) = stdouts.remove(cmd) else {
+        return;
+    };
+    output.insert(cmd.clone(), stdout);
+    if let Some(subs) = subcommands.get(cmd) {
+        for sub in subs {
+            let child = Cmd {
+                cmd: cmd.cmd,
+                subcommands: cmd
+                    .subcommands
+                    .iter()
+                    .cloned()
+                    .chain(once(sub.clone()))
+                    .collect(),
+            };
+            insert_ordered(&child, subcommands, stdouts, output);
+        }
+    }
+}
+
 /// Returns the subcommands and help output for a command.
-fn get_entry(cmd: &Cmd) -> io::Result<(Vec<String>, String)> {
+fn get_entry(cmd: &Cmd) -> HelpResult {
     let output = Command::new(cmd.cmd)
         .args(&cmd.subcommands)
         .arg("--help")
@@ -225,17 +394,157 @@ fn parse_sub_commands(s: &str) -> Vec<String> {
         .unwrap_or_default() // Return an empty Vec if "Commands:" was not found
 }
 
+/// A single flag parsed from the `Options:` section of a command's help output.
+#[derive(Debug, Clone)]
+struct Flag {
+    /// Long form including the leading dashes, e.g. `--root`.
+    long: String,
+    /// Optional short form including the leading dash, e.g. `-r`.
+    short: Option<String>,
+    /// Value placeholder, e.g. `<PATH>` (`None` when the flag takes no value).
+    value: Option<String>,
+    /// Description text.
+    help: String,
+}
+
+impl Flag {
+    /// Builds a flag from an options-section entry, returning `None` for positionals.
+    fn from_entry(e: &OptionEntry) -> Option<Self> {
+        if !e.flags.starts_with('-') {
+            return None;
+        }
+        let mut long = String::new();
+        let mut short = None;
+        for part in e.flags.split(',').map(str::trim) {
+            if part.starts_with("--") {
+                long = part.to_string();
+            } else if part.starts_with('-') {
+                short = Some(part.to_string());
+            }
+        }
+        // A short-only flag keys on its short form.
+        if long.is_empty() {
+            long = short.clone().unwrap_or_default();
+        }
+        Some(Flag {
+            long,
+            short,
+            value: (!e.value.is_empty()).then(|| e.value.clone()),
+            help: e.description.clone(),
+        })
+    }
+
+    /// Whether the flag expects a value.
+    fn takes_value(&self) -> bool {
+        self.value.is_some()
+    }
+}
+
+/// A positional argument parsed from the `Arguments:` section of a command's help output.
+#[derive(Debug, Clone)]
+struct Arg {
+    /// Placeholder name, e.g. `<COMMAND>`.
+    name: String,
+    /// Description text.
+    help: String,
+}
+
+impl Arg {
+    fn from_entry(e: &OptionEntry) -> Self {
+        let name = if e.value.is_empty() {
+            e.flags.clone()
+        } else {
+            format!("{} {}", e.flags, e.value)
+        };
+        Arg {
+            name: name.trim().to_string(),
+            help: e.description.clone(),
+        }
+    }
+}
+
+/// Returns the flags listed across every options group of a command's help output.
+///
+/// clap's `help_heading` splits options into multiple groups (`Options:`, `Build options:`,
+/// …); all of them are collected here.
+fn parse_flags(s: &str) -> Vec<Flag> {
+    split_sections(s)
+        .into_iter()
+        .filter(|(heading, _)| is_option_heading(heading))
+        .flat_map(|(_, body)| parse_entries(&body))
+        .filter_map(|e| Flag::from_entry(&e))
+        .collect()
+}
+
+/// Returns the positional arguments listed in the `Arguments:` section.
+fn parse_args(s: &str) -> Vec<Arg> {
+    split_sections(s)
+        .into_iter()
+        .filter(|(heading, _)| heading == "Arguments")
+        .flat_map(|(_, body)| parse_entries(&body))
+        .map(|e| Arg::from_entry(&e))
+        .collect()
+}
+
+/// Whether a section heading names a group of options (anything but commands or arguments).
+fn is_option_heading(heading: &str) -> bool {
+    heading != "Commands" && heading != "Arguments"
+}
+
+/// A structured model of a single command, parsed from its `--help` output.
+#[derive(Debug)]
+struct CommandModel {
+    /// Full command path including the binary name, e.g. `["forge", "build"]`.
+    path: Vec<String>,
+    flags: Vec<Flag>,
+    args: Vec<Arg>,
+    subcommands: Vec<String>,
+}
+
+impl CommandModel {
+    /// Parses a command model from its path and `--help` output.
+    fn parse(path: Vec<String>, help: &str) -> Self {
+        CommandModel {
+            path,
+            flags: parse_flags(help),
+            args: parse_args(help),
+            subcommands: parse_sub_commands(help),
+        }
+    }
+
+    /// The command path joined with spaces, e.g. `forge build`.
+    fn key(&self) -> String {
+        self.path.join(" ")
+    }
+}
+
 /// Writes the markdown for a command to out_dir.
-fn cmd_markdown(out_dir: &Path, cmd: &Cmd, stdout: &str) -> io::Result<()> {
-    let out = format!("# {}\n\n{}", cmd, help_markdown(cmd, stdout));
+fn cmd_markdown(
+    out_dir: &Path,
+    cmd: &Cmd,
+    stdout: &str,
+    structured: bool,
+    generated: &HashSet<String>,
+) -> io::Result<()> {
+    let body = if structured {
+        help_markdown_structured(cmd, stdout, generated)
+    } else {
+        help_markdown(cmd, stdout)
+    };
+    let out = format!("# {}\n\n{}", cmd, body);
 
-    let out_path = out_dir.join(cmd.to_string().replace(" ", "/"));
+    let out_path = out_dir.join(cmd_md_path(cmd));
     fs::create_dir_all(out_path.parent().unwrap())?;
-    write_file(&out_path.with_extension("md"), &out)?;
+    write_file(&out_path, &out)?;
 
     Ok(())
 }
 
+/// The markdown output path for a command, relative to the output directory, e.g. `forge/build.md`.
+fn cmd_md_path(cmd: &Cmd) -> PathBuf {
+    PathBuf::from(cmd.to_string().replace(' ', "/")).with_extension("md")
+}
+
 /// Returns the markdown for a command's help output.
 fn help_markdown(cmd: &Cmd, stdout: &str) -> String {
     let (description, s) = parse_description(stdout);
@@ -247,6 +556,196 @@ fn help_markdown(cmd: &Cmd, stdout: &str) -> String {
     )
 }
 
+/// A single entry parsed from an `Options:` or `Arguments:` section.
+#[derive(Debug)]
+struct OptionEntry {
+    /// Flag column, e.g. `-r, --root` or a positional like `<COMMAND>`.
+    flags: String,
+    /// Value placeholder, e.g. `<PATH>` or `<PATH>...` (empty when the flag takes none).
+    value: String,
+    /// Description, with wrapped continuation lines joined into a single line.
+    description: String,
+}
+
+/// Renders a command's help output with its options and arguments as markdown tables.
+fn help_markdown_structured(cmd: &Cmd, stdout: &str, generated: &HashSet<String>) -> String {
+    // clap prints the (possibly multi-line) long description before the usage line.
+    let (description, rest) = match stdout.find("Usage:") {
+        Some(idx) => (stdout[..idx].trim(), &stdout[idx..]),
+        None => ("", stdout),
+    };
+    let usage = rest.lines().next().unwrap_or("").trim();
+
+    let mut out = String::new();
+    if !description.is_empty() {
+        out.push_str(description);
+        out.push_str("\n\n");
+    }
+    if usage.starts_with("Usage:") {
+        out.push_str(&format!("**Usage:** `{}`\n", usage.trim_start_matches("Usage:").trim()));
+    }
+
+    // Render each section in order; `help_heading` groups become their own table.
+    for (heading, body) in split_sections(rest) {
+        if heading == "Commands" {
+            continue; // subcommands are rendered as links below
+        }
+        let entries = parse_entries(&body);
+        if entries.is_empty() {
+            continue;
+        }
+
+        if heading == "Arguments" {
+            out.push_str("\n### Arguments\n\n| Argument | Description |\n|---|---|\n");
+            for e in &entries {
+                let name = format!("{} {}", e.flags, e.value);
+                out.push_str(&format!(
+                    "| `{}` | {} |\n",
+                    escape_table_code(name.trim()),
+                    escape_table_cell(&e.description)
+                ));
+            }
+        } else {
+            out.push_str(&format!(
+                "\n### {heading}\n\n| Flag | Value | Description |\n|---|---|---|\n"
+            ));
+            for e in &entries {
+                let value = if e.value.is_empty() {
+                    String::new()
+                } else {
+                    format!("`{}`", escape_table_code(&e.value))
+                };
+                out.push_str(&format!(
+                    "| `{}` | {} | {} |\n",
+                    escape_table_code(&e.flags),
+                    value,
+                    escape_table_cell(&e.description)
+                ));
+            }
+        }
+    }
+
+    let subcmds = parse_sub_commands(stdout);
+    if !subcmds.is_empty() {
+        out.push_str("\n### Subcommands\n\n");
+        // Links are relative to this page's own directory, i.e. the command's leaf name.
+        let leaf = cmd.subcommands.last().map_or(cmd.command_name(), |s| s.as_str());
+        for sub in &subcmds {
+            // Skip subcommands that were not generated (e.g. skipped after a failure).
+            if !generated.contains(&format!("{cmd} {sub}")) {
+                continue;
+            }
+            out.push_str(&format!("- [`{cmd} {sub}`](./{leaf}/{sub}.md)\n"));
+        }
+    }
+
+    out
+}
+
+/// Escapes a description so it can live inside a single markdown table cell.
+fn escape_table_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Escapes a value rendered inside a backtick code span in a markdown table cell.
+fn escape_table_code(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// Splits help output into its sections, keyed by heading (without the trailing colon).
+///
+/// A heading is a non-indented line ending in `:` (e.g. `Options:`, `Build options:`,
+/// `Commands:`); the `Usage:` line does not qualify as it ends with the usage string. Any
+/// preamble before the first heading (description, usage) is not returned.
+fn split_sections(s: &str) -> Vec<(String, String)> {
+    let mut sections = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    for line in s.lines() {
+        let is_header = !line.is_empty()
+            && !line.starts_with(char::is_whitespace)
+            && line.trim_end().ends_with(':');
+
+        if is_header {
+            if let Some((heading, body)) = current.take() {
+                sections.push((heading, body.join("\n")));
+            }
+            let heading = line.trim_end().trim_end_matches(':').to_string();
+            current = Some((heading, Vec::new()));
+        } else if let Some((_, body)) = current.as_mut() {
+            body.push(line);
+        }
+    }
+
+    if let Some((heading, body)) = current.take() {
+        sections.push((heading, body.join("\n")));
+    }
+    sections
+}
+
+/// Parses the entries of an `Options:`/`Arguments:` section body into [`OptionEntry`]s.
+///
+/// An entry begins at a line indented into the flag column (a leading `-`, `<`, or `[`);
+/// lines indented further are continuation lines of the current entry's description. A
+/// non-indented line ending in `:` (a stray `help_heading` group boundary) flushes the
+/// current entry without being folded into its description.
+fn parse_entries(section: &str) -> Vec<OptionEntry> {
+    let gap = regex!(r"\s{2,}");
+    let mut entries = Vec::new();
+    let mut current: Option<OptionEntry> = None;
+
+    for line in section.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if indent == 0 && trimmed.ends_with(':') && !trimmed.is_empty() {
+            if let Some(e) = current.take() {
+                entries.push(e);
+            }
+            continue;
+        }
+
+        let is_start = indent <= 6
+            && (trimmed.starts_with('-') || trimmed.starts_with('<') || trimmed.starts_with('['));
+
+        if is_start {
+            if let Some(e) = current.take() {
+                entries.push(e);
+            }
+            let (head, desc) = match gap.find(trimmed) {
+                Some(m) => (trimmed[..m.start()].trim(), trimmed[m.end()..].trim()),
+                None => (trimmed.trim(), ""),
+            };
+            let (flags, value) = split_flags_value(head);
+            current = Some(OptionEntry {
+                flags,
+                value,
+                description: desc.to_string(),
+            });
+        } else if !trimmed.is_empty() {
+            if let Some(e) = current.as_mut() {
+                if !e.description.is_empty() {
+                    e.description.push(' ');
+                }
+                e.description.push_str(trimmed);
+            }
+        }
+    }
+
+    if let Some(e) = current.take() {
+        entries.push(e);
+    }
+    entries
+}
+
+/// Splits an entry head such as `-r, --root <PATH>` into its flags and value placeholder.
+fn split_flags_value(head: &str) -> (String, String) {
+    match head.find(" <").or_else(|| head.find(" [")) {
+        Some(pos) => (head[..pos].trim().to_string(), head[pos..].trim().to_string()),
+        None => (head.to_string(), String::new()),
+    }
+}
+
 /// Splits the help output into a description and the rest.
 fn parse_description(s: &str) -> (&str, &str) {
     match s.find("Usage:") {
@@ -324,7 +823,518 @@ fn preprocess_help(s: &str) -> Cow<'_, str> {
     s
 }
 
-#[derive(Hash, Debug, PartialEq, Eq)]
+/// Builds a keyed set of command models from the freshly parsed help output.
+fn build_models(output: &IndexMap<Cmd, String>) -> IndexMap<String, CommandModel> {
+    output
+        .iter()
+        .map(|(cmd, stdout)| {
+            let path: Vec<String> = once(cmd.command_name().to_string())
+                .chain(cmd.subcommands.iter().cloned())
+                .collect();
+            let model = CommandModel::parse(path, stdout);
+            (model.key(), model)
+        })
+        .collect()
+}
+
+/// Loads command models from a previously generated (raw) output directory.
+///
+/// Each markdown page embeds its `--help` output in a fenced block; the command path is
+/// recovered from the file's path relative to `dir`.
+fn load_baseline_models(dir: &Path) -> io::Result<IndexMap<String, CommandModel>> {
+    let mut files = Vec::new();
+    collect_markdown(dir, &mut files)?;
+
+    let mut models = IndexMap::new();
+    for file in files {
+        let rel = file.strip_prefix(dir).unwrap_or(&file).with_extension("");
+        let path: Vec<String> = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        if path.is_empty() {
+            continue;
+        }
+        let md = fs::read_to_string(&file)?;
+        if let Some(help) = extract_help_block(&md) {
+            let model = CommandModel::parse(path, &help);
+            models.insert(model.key(), model);
+        }
+    }
+    Ok(models)
+}
+
+/// Recursively collects markdown files under `dir`, skipping the generated meta files.
+fn collect_markdown(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_markdown(&path, out)?;
+        } else if path.extension().is_some_and(|e| e == "md") {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if !matches!(name, "SUMMARY.md" | "README.md" | "CHANGELOG.md") {
+                out.push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the `--help` text from a generated markdown page's fenced block.
+fn extract_help_block(md: &str) -> Option<String> {
+    let start = md.find("```bash")?;
+    let after = &md[start..];
+    let body_start = after.find('\n')? + 1;
+    let body = &after[body_start..];
+    let end = body.find("```")?;
+    // Drop the leading `$ <cmd> --help` invocation line.
+    Some(body[..end].split_once('\n').map_or("", |x| x.1).to_string())
+}
+
+/// Computes a markdown changelog describing how the CLI surface changed.
+fn diff_models(
+    baseline: &IndexMap<String, CommandModel>,
+    current: &IndexMap<String, CommandModel>,
+) -> String {
+    let mut out = String::from("# CLI Changelog\n\n");
+
+    // Commands present in the new surface, in generation order, then removed ones.
+    let keys = current
+        .keys()
+        .chain(baseline.keys().filter(|k| !current.contains_key(*k)));
+
+    for key in keys {
+        match (baseline.get(key), current.get(key)) {
+            (None, Some(_)) => {
+                out.push_str(&format!("## `{key}` (added)\n\n"));
+            }
+            (Some(_), None) => {
+                out.push_str(&format!("## `{key}` (removed)\n\n"));
+            }
+            (Some(old), Some(new)) => {
+                if let Some(section) = diff_command(old, new) {
+                    out.push_str(&format!("## `{key}`\n\n{section}\n"));
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    out
+}
+
+/// Returns the per-command changelog section, or `None` when nothing changed.
+fn diff_command(old: &CommandModel, new: &CommandModel) -> Option<String> {
+    let mut out = String::new();
+
+    let added_subs: Vec<_> = new
+        .subcommands
+        .iter()
+        .filter(|s| !old.subcommands.contains(s))
+        .collect();
+    let removed_subs: Vec<_> = old
+        .subcommands
+        .iter()
+        .filter(|s| !new.subcommands.contains(s))
+        .collect();
+
+    let added_flags: Vec<_> = new
+        .flags
+        .iter()
+        .filter(|f| !old.flags.iter().any(|o| o.long == f.long))
+        .collect();
+    let removed_flags: Vec<_> = old
+        .flags
+        .iter()
+        .filter(|f| !new.flags.iter().any(|n| n.long == f.long))
+        .collect();
+
+    let added_args: Vec<_> = new
+        .args
+        .iter()
+        .filter(|a| !old.args.iter().any(|o| o.name == a.name))
+        .collect();
+    let removed_args: Vec<_> = old
+        .args
+        .iter()
+        .filter(|a| !new.args.iter().any(|n| n.name == a.name))
+        .collect();
+
+    let changed_args: Vec<_> = new
+        .args
+        .iter()
+        .filter_map(|a| {
+            old.args
+                .iter()
+                .find(|o| o.name == a.name)
+                .filter(|o| o.help != a.help)
+                .map(|_| format!("- `{}`: description changed", a.name))
+        })
+        .collect();
+
+    let mut changed_flags = Vec::new();
+    for f in &new.flags {
+        if let Some(o) = old.flags.iter().find(|o| o.long == f.long) {
+            if o.value != f.value {
+                changed_flags.push(format!(
+                    "- `{}`: value `{}` → `{}`",
+                    f.long,
+                    o.value.clone().unwrap_or_default(),
+                    f.value.clone().unwrap_or_default()
+                ));
+            } else if o.help != f.help {
+                changed_flags.push(format!("- `{}`: description changed", f.long));
+            }
+        }
+    }
+
+    let section = |title: &str, items: Vec<String>| {
+        if items.is_empty() {
+            String::new()
+        } else {
+            format!("### {title}\n{}\n", items.join("\n"))
+        }
+    };
+
+    out.push_str(&section(
+        "Added subcommands",
+        added_subs.iter().map(|s| format!("- `{s}`")).collect(),
+    ));
+    out.push_str(&section(
+        "Removed subcommands",
+        removed_subs.iter().map(|s| format!("- `{s}`")).collect(),
+    ));
+    out.push_str(&section(
+        "Added arguments",
+        added_args
+            .iter()
+            .map(|a| format!("- `{}`", a.name))
+            .collect(),
+    ));
+    out.push_str(&section(
+        "Removed arguments",
+        removed_args
+            .iter()
+            .map(|a| format!("- `{}`", a.name))
+            .collect(),
+    ));
+    out.push_str(&section(
+        "Added flags",
+        added_flags
+            .iter()
+            .map(|f| format!("- `{}`", f.long))
+            .collect(),
+    ));
+    out.push_str(&section(
+        "Removed flags",
+        removed_flags
+            .iter()
+            .map(|f| format!("- `{}`", f.long))
+            .collect(),
+    ));
+    out.push_str(&section("Changed arguments", changed_args));
+    out.push_str(&section("Changed flags", changed_flags));
+
+    (!out.is_empty()).then_some(out)
+}
+
+/// Packs the generated output tree into a gzip-compressed tarball.
+///
+/// A `manifest.txt` listing each command and its output path is added at the archive root,
+/// followed by every generated file keyed by its path relative to `out_dir`. When `clean`
+/// is set, the packed files are removed afterwards.
+fn create_archive(
+    out_dir: &Path,
+    archive_path: &Path,
+    output: &IndexMap<Cmd, String>,
+    clean: bool,
+) -> io::Result<()> {
+    let mut files = Vec::new();
+    collect_files(out_dir, &mut files)?;
+    // Don't pack the archive into itself if it lives inside out_dir.
+    files.retain(|f| f != archive_path);
+    // Pack in a stable order so archives are reproducible across runs.
+    files.sort();
+
+    let manifest = archive_manifest(output);
+
+    if let Some(parent) = archive_path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    let enc = GzEncoder::new(File::create(archive_path)?, Compression::default());
+    let mut builder = tar::Builder::new(enc);
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, "manifest.txt", manifest.as_bytes())?;
+
+    for file in &files {
+        let rel = file.strip_prefix(out_dir).unwrap_or(file);
+        builder.append_path_with_name(file, rel)?;
+    }
+
+    builder.into_inner()?.finish()?;
+
+    if clean {
+        for file in &files {
+            match fs::remove_file(file) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the archive manifest mapping each command to its generated markdown path.
+fn archive_manifest(output: &IndexMap<Cmd, String>) -> String {
+    let mut manifest = String::from("# CLI reference archive manifest\n");
+    for cmd in output.keys() {
+        manifest.push_str(&format!("{cmd}\t{}\n", cmd_md_path(cmd).display()));
+    }
+    manifest
+}
+
+/// Recursively collects every file under `dir`.
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Shell flavours supported by the completion generator.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// A command node flattened from the parsed help tree, used to emit completions.
+struct CompletionNode {
+    /// Full command path including the binary name, e.g. `["forge", "build"]`.
+    path: Vec<String>,
+    /// Flags valid at this node.
+    flags: Vec<Flag>,
+    /// Direct subcommand names available at this node.
+    subcommands: Vec<String>,
+}
+
+/// Flattens the parsed help tree into one [`CompletionNode`] per command.
+fn completion_nodes(output: &IndexMap<Cmd, String>) -> Vec<CompletionNode> {
+    output
+        .iter()
+        .map(|(cmd, stdout)| {
+            let path = once(cmd.command_name().to_string())
+                .chain(cmd.subcommands.iter().cloned())
+                .collect();
+            CompletionNode {
+                path,
+                flags: parse_flags(stdout),
+                subcommands: parse_sub_commands(stdout),
+            }
+        })
+        .collect()
+}
+
+/// Writes one completion script per root command into out_dir.
+fn generate_completions(
+    out_dir: &Path,
+    shell: Shell,
+    output: &IndexMap<Cmd, String>,
+) -> io::Result<()> {
+    let nodes = completion_nodes(output);
+
+    // Group nodes by their root binary, preserving discovery order.
+    let mut roots: IndexMap<String, Vec<&CompletionNode>> = IndexMap::new();
+    for node in &nodes {
+        roots.entry(node.path[0].clone()).or_default().push(node);
+    }
+
+    for (bin, nodes) in &roots {
+        let (content, file_name) = match shell {
+            Shell::Bash => (completions_bash(bin, nodes), format!("{bin}.bash")),
+            Shell::Zsh => (completions_zsh(bin, nodes), format!("_{bin}")),
+            Shell::Fish => (completions_fish(bin, nodes), format!("{bin}.fish")),
+            Shell::Powershell => (completions_powershell(bin, nodes), format!("_{bin}.ps1")),
+        };
+        write_file(&out_dir.join(file_name), &content)?;
+    }
+
+    Ok(())
+}
+
+/// Returns every option string for a node (each flag's long form followed by its short form).
+fn node_opts(node: &CompletionNode) -> Vec<String> {
+    node.flags
+        .iter()
+        .flat_map(|f| once(f.long.clone()).chain(f.short.clone()))
+        .collect()
+}
+
+/// Emits a single `_<bin>` bash function that switches on the detected command path.
+fn completions_bash(bin: &str, nodes: &[&CompletionNode]) -> String {
+    let mut cases = String::new();
+    for node in nodes {
+        cases.push_str(&format!(
+            "        \"{}\")\n            opts=\"{}\"\n            subcmds=\"{}\"\n            ;;\n",
+            node.path.join(" "),
+            node_opts(node).join(" "),
+            node.subcommands.join(" "),
+        ));
+    }
+
+    format!(
+        r#"_{bin}() {{
+    local cur cmd i
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    cmd="{bin}"
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${{COMP_WORDS[i]}}" in
+            -*) ;;
+            *) cmd="${{cmd}} ${{COMP_WORDS[i]}}" ;;
+        esac
+    done
+
+    local opts=""
+    local subcmds=""
+    case "${{cmd}}" in
+{cases}    esac
+
+    if [[ "${{cur}}" == -* ]]; then
+        COMPREPLY=( $(compgen -W "${{opts}}" -- "${{cur}}") )
+    else
+        COMPREPLY=( $(compgen -W "${{subcmds}}" -- "${{cur}}") )
+    fi
+}}
+complete -F _{bin} {bin}
+"#,
+    )
+}
+
+/// Emits a `#compdef`-prefixed `_<bin>` using an `_arguments` block per subcommand.
+fn completions_zsh(bin: &str, nodes: &[&CompletionNode]) -> String {
+    let mut cases = String::new();
+    for node in nodes {
+        let mut specs: Vec<String> = node
+            .flags
+            .iter()
+            .flat_map(|f| {
+                let tail = if f.takes_value() { "=[]:value:" } else { "[]" };
+                once(format!("'{}{}'", f.long, tail))
+                    .chain(f.short.clone().map(|s| format!("'{}{}'", s, tail)))
+            })
+            .collect();
+        if !node.subcommands.is_empty() {
+            specs.push(format!("'1: :({})'", node.subcommands.join(" ")));
+            specs.push("'*:: :->args'".to_string());
+        }
+        cases.push_str(&format!(
+            "        \"{}\")\n            _arguments {} && return\n            ;;\n",
+            node.path.join(" "),
+            specs.join(" \\\n                "),
+        ));
+    }
+
+    format!(
+        r#"#compdef {bin}
+_{bin}() {{
+    local cmd="{bin}"
+    local i
+    for (( i = 2; i < CURRENT; i++ )); do
+        case "${{words[i]}}" in
+            -*) ;;
+            *) cmd="${{cmd}} ${{words[i]}}" ;;
+        esac
+    done
+
+    case "${{cmd}}" in
+{cases}    esac
+}}
+compdef _{bin} {bin}
+"#,
+    )
+}
+
+/// Emits one `complete` line per subcommand and flag, gated by the seen subcommand.
+fn completions_fish(bin: &str, nodes: &[&CompletionNode]) -> String {
+    let mut lines = format!("complete -c {bin} -f\n");
+    for node in nodes {
+        let cond = if node.path.len() == 1 {
+            "__fish_use_subcommand".to_string()
+        } else {
+            format!("__fish_seen_subcommand_from {}", node.path.last().unwrap())
+        };
+        for sub in &node.subcommands {
+            lines.push_str(&format!("complete -c {bin} -n '{cond}' -a '{sub}'\n"));
+        }
+        for flag in &node.flags {
+            let long = flag.long.trim_start_matches('-');
+            let short = flag
+                .short
+                .as_ref()
+                .map(|s| format!(" -s {}", s.trim_start_matches('-')))
+                .unwrap_or_default();
+            let takes = if flag.takes_value() { " -r" } else { "" };
+            lines.push_str(&format!(
+                "complete -c {bin} -n '{cond}' -l {long}{short}{takes}\n"
+            ));
+        }
+    }
+    lines
+}
+
+/// Emits a PowerShell `Register-ArgumentCompleter` block switching on the command path.
+fn completions_powershell(bin: &str, nodes: &[&CompletionNode]) -> String {
+    let mut cases = String::new();
+    for node in nodes {
+        let mut items = node.subcommands.clone();
+        items.extend(node_opts(node));
+        let quoted: Vec<String> = items.iter().map(|i| format!("'{i}'")).collect();
+        cases.push_str(&format!(
+            "        '{}' {{ $completions = @({}) }}\n",
+            node.path.join(" "),
+            quoted.join(", "),
+        ));
+    }
+
+    format!(
+        r#"using namespace System.Management.Automation
+
+Register-ArgumentCompleter -Native -CommandName '{bin}' -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $command = @($commandAst.CommandElements |
+        Where-Object {{ "$_" -notmatch '^-' }} |
+        ForEach-Object {{ $_.ToString() }}) -join ' '
+
+    $completions = @()
+    switch -exact ($command) {{
+{cases}    }}
+
+    $completions |
+        Where-Object {{ $_ -like "$wordToComplete*" }} |
+        ForEach-Object {{ [CompletionResult]::new($_, $_, 'ParameterValue', $_) }}
+}}
+"#,
+    )
+}
+
+#[derive(Hash, Debug, Clone, PartialEq, Eq)]
 struct Cmd<'a> {
     /// path to binary (e.g. ./target/debug/reth)
     cmd: &'a Path,